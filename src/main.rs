@@ -1,7 +1,9 @@
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufWriter, Cursor, Read, Write};
-use std::{env, error::Error, fs, process::exit};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::{env, error::Error, fs, process::exit, thread};
 
 use ::image::io::Reader as ImageReader;
 use ipp::prelude::*;
@@ -12,46 +14,250 @@ const PAGE_WIDTH_MM: f32 = 101.6;   // 4 inches in mm
 const PAGE_HEIGHT_MM: f32 = 152.4;  // 6 inches in mm
 // Use the legacy media value that maps to a 4×6 output.
 const DEFAULT_MEDIA: &str = "w288h432";
+// Default page size for non-image (PDF) documents in a multi-file job.
+const A4_WIDTH_MM: f32 = 210.0;
+const A4_HEIGHT_MM: f32 = 297.0;
+const A4_MEDIA: &str = "iso_a4_210x297mm";
 const PRINT_COLOR_MODE: &str = "color";
 const PRINT_QUALITY: i32 = 4; // Normal quality
 
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const JOB_POLL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// One input file destined for the job, with the media size it should be printed at.
+struct FileEntry {
+    path: String,
+    width_mm: f32,
+    height_mm: f32,
+    media: &'static str,
+    is_image: bool,
+    is_svg: bool,
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<_> = env::args().collect();
     if args.len() < 3 {
-        eprintln!("Usage: {} printer_uri filename [key=value ...]", args[0]);
+        eprintln!("Usage: {} printer_uri filename [filename ...] [key=value ...]", args[0]);
         exit(1);
     }
 
     // Parse the printer URI.
     let uri: Uri = args[1].parse().map_err(|e| format!("Invalid printer URI: {}", e))?;
 
+    // Trailing arguments are either more input files or key=value options (options always
+    // contain '=', filenames never do).
+    let (option_args, filenames): (Vec<String>, Vec<String>) =
+        args[2..].iter().cloned().partition(|a| a.contains('='));
+    if filenames.is_empty() {
+        eprintln!("No input files given");
+        exit(1);
+    }
+    let options = parse_key_value_options(&option_args);
+    let pages_per_sheet: u32 = options
+        .get("pages-per-sheet")
+        .map(|v| v.parse().map_err(|_| format!("Invalid pages-per-sheet: {}", v)))
+        .transpose()?
+        .unwrap_or(1);
+
     // (Optional) Query and print the printer’s attributes.
     let printer_attrs = get_printer_attributes(&uri)?;
     println!("Printer attributes:");
     debug_print_printer_attributes(&printer_attrs);
 
-    // Convert the input file (image or PDF) to PDF bytes.
-    let pdf_data = if args[2].ends_with(".jpg")
-        || args[2].ends_with(".jpeg")
-        || args[2].ends_with(".png")
-    {
-        println!("Converting image to PDF...");
-        convert_image_to_pdf(&args[2])?
+    let entries: Vec<FileEntry> = filenames.into_iter().map(|path| {
+        let is_image = path.ends_with(".jpg") || path.ends_with(".jpeg") || path.ends_with(".png");
+        let is_svg = path.ends_with(".svg");
+        let (width_mm, height_mm, media) = if is_image || is_svg {
+            (PAGE_WIDTH_MM, PAGE_HEIGHT_MM, DEFAULT_MEDIA)
+        } else {
+            (A4_WIDTH_MM, A4_HEIGHT_MM, A4_MEDIA)
+        };
+        FileEntry { path, width_mm, height_mm, media, is_image, is_svg }
+    }).collect();
+
+    let mut outcomes = Vec::new();
+    if entries.len() == 1 {
+        // The one-entry case: a single Print-Job exactly as before.
+        let entry = &entries[0];
+        let document_bytes = document_bytes_for_entry(entry, pages_per_sheet)?;
+        outcomes.push(submit_print_job(&uri, &printer_attrs, entry, 1, &document_bytes, pages_per_sheet)?);
     } else {
+        // IPP's media/media-col is a single job-level value, so entries with different media
+        // sizes can't share one Print-Job; group same-sized entries into one multi-page document
+        // each and send one print job per group instead.
+        let groups = group_entries_by_media(&entries);
+        println!("Splitting {} file(s) into {} print job(s) grouped by media size", entries.len(), groups.len());
+        for group in groups {
+            let document_bytes = document_bytes_for_group(&group, pages_per_sheet)?;
+            outcomes.push(submit_print_job(&uri, &printer_attrs, group[0], group.len(), &document_bytes, pages_per_sheet)?);
+        }
+    }
+
+    if outcomes.iter().any(|o| matches!(o, JobOutcome::Canceled | JobOutcome::Aborted)) {
+        exit(1);
+    }
+    Ok(())
+}
+
+/// Groups entries by media size, preserving first-seen order, so same-sized files end up in one
+/// multi-page document/job together.
+fn group_entries_by_media(entries: &[FileEntry]) -> Vec<Vec<&FileEntry>> {
+    let mut groups: Vec<Vec<&FileEntry>> = Vec::new();
+    for entry in entries {
+        match groups.iter_mut().find(|g| g[0].media == entry.media) {
+            Some(group) => group.push(entry),
+            None => groups.push(vec![entry]),
+        }
+    }
+    groups
+}
+
+/// Renders the PDF bytes for a single-file job (the one-entry case). SVG and existing-PDF inputs
+/// have no N-up imposition support, so `pages_per_sheet > 1` is rejected for them instead of
+/// being silently ignored.
+fn document_bytes_for_entry(entry: &FileEntry, pages_per_sheet: u32) -> Result<Vec<u8>, Box<dyn Error>> {
+    if entry.is_image {
+        println!("Converting image to PDF ({} up)...", pages_per_sheet);
+        convert_image_to_pdf(&entry.path, pages_per_sheet)
+    } else if entry.is_svg {
+        reject_imposition_for_non_image(&entry.path, pages_per_sheet)?;
+        println!("Converting SVG to vector PDF...");
+        convert_svg_to_pdf(&entry.path, PAGE_WIDTH_MM, PAGE_HEIGHT_MM)
+    } else {
+        reject_imposition_for_non_image(&entry.path, pages_per_sheet)?;
         println!("Using existing PDF file...");
-        let mut file = fs::File::open(&args[2])?;
+        let mut file = fs::File::open(&entry.path)?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
-        buffer
-    };
+        Ok(buffer)
+    }
+}
+
+/// Fails clearly if imposition was requested for a source kind (SVG, existing PDF) this tool
+/// can't impose multiple-per-sheet, rather than silently printing one-up anyway.
+fn reject_imposition_for_non_image(path: &str, pages_per_sheet: u32) -> Result<(), Box<dyn Error>> {
+    if pages_per_sheet > 1 {
+        return Err(format!(
+            "pages-per-sheet={} was requested for '{}', but N-up imposition is only supported for \
+             image sources. Print it with pages-per-sheet=1.",
+            pages_per_sheet, path
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Renders the PDF bytes for one media-size group: a combined multi-page PDF, or the raw bytes of
+/// the lone existing PDF file if it's the only thing in the group. An existing PDF can never be
+/// merged with other content (this tool has no PDF-page merge support), so any group mixing a PDF
+/// with other files — or with a second PDF — is rejected explicitly rather than being fed through
+/// the image pipeline. Multi-file image jobs are imposed `pages_per_sheet` source pages per output
+/// sheet; SVG entries (imposition unsupported) get one sheet each and reject `pages_per_sheet > 1`.
+fn document_bytes_for_group(group: &[&FileEntry], pages_per_sheet: u32) -> Result<Vec<u8>, Box<dyn Error>> {
+    let existing_pdfs: Vec<&&FileEntry> = group.iter().filter(|e| !e.is_image && !e.is_svg).collect();
+    if !existing_pdfs.is_empty() && group.len() > 1 {
+        return Err(format!(
+            "Can't combine an existing PDF with other files in one job (no PDF-page merge support): {}. \
+             Print it in a separate job.",
+            existing_pdfs.iter().map(|e| e.path.as_str()).collect::<Vec<_>>().join(", ")
+        )
+        .into());
+    }
+    if group.len() == 1 {
+        return document_bytes_for_entry(group[0], pages_per_sheet);
+    }
+    if pages_per_sheet > 1 {
+        if let Some(svg_entry) = group.iter().find(|e| e.is_svg) {
+            reject_imposition_for_non_image(&svg_entry.path, pages_per_sheet)?;
+        }
+    }
+
+    println!("Building a {}-page PDF at {}...", group.len(), group[0].media);
+    let pages = compose_group_pages(group, pages_per_sheet);
+    build_multi_page_pdf(&pages, pages_per_sheet)
+}
+
+/// Chunks a multi-entry group's image files into `pages_per_sheet`-sized batches, each composed
+/// onto one output sheet (the "N source pages onto one sheet" imposition the request asks for);
+/// SVG entries pass through unchanged, one per output sheet.
+fn compose_group_pages(group: &[&FileEntry], pages_per_sheet: u32) -> Vec<(PageContent, f32, f32)> {
+    let mut pages = Vec::new();
+    let mut image_batch: Vec<&FileEntry> = Vec::new();
+
+    for entry in group {
+        if entry.is_svg {
+            flush_image_batch(&mut image_batch, &mut pages);
+            pages.push((PageContent::Svg(entry.path.clone()), entry.width_mm, entry.height_mm));
+            continue;
+        }
+        image_batch.push(entry);
+        if image_batch.len() == pages_per_sheet.max(1) as usize {
+            flush_image_batch(&mut image_batch, &mut pages);
+        }
+    }
+    flush_image_batch(&mut image_batch, &mut pages);
+    pages
+}
+
+fn flush_image_batch(batch: &mut Vec<&FileEntry>, pages: &mut Vec<(PageContent, f32, f32)>) {
+    if batch.is_empty() {
+        return;
+    }
+    let (width_mm, height_mm) = (batch[0].width_mm, batch[0].height_mm);
+    let paths = batch.iter().map(|e| e.path.clone()).collect();
+    pages.push((PageContent::ImposedImages(paths), width_mm, height_mm));
+    batch.clear();
+}
+
+/// Negotiates the document format for one entry/group against the printer's advertised
+/// capabilities, rasterizing to PWG Raster only when the source is a single image file.
+fn negotiate_and_finalize(
+    printer_attrs: &IppAttributes,
+    entry: &FileEntry,
+    group_len: usize,
+    document_bytes: Vec<u8>,
+) -> Result<(Vec<u8>, &'static str), Box<dyn Error>> {
+    let negotiated_format = negotiate_document_format(printer_attrs);
+    Ok(match &negotiated_format {
+        NegotiatedFormat::Pdf => (document_bytes, "application/pdf"),
+        NegotiatedFormat::PwgRaster { resolution, color_space } if entry.is_image && group_len == 1 => {
+            println!(
+                "Printer doesn't support PDF; rasterizing to PWG Raster at {}x{} dpi, {:?}",
+                resolution.0, resolution.1, color_space
+            );
+            (rasterize_image_to_pwg(&entry.path, *resolution, *color_space)?, "image/pwg-raster")
+        }
+        NegotiatedFormat::PwgRaster { .. } => {
+            println!(
+                "Printer doesn't support PDF and no PWG renderer is available for this document; \
+                 sending PDF anyway."
+            );
+            (document_bytes, "application/pdf")
+        }
+    })
+}
+
+/// Submits one Print-Job for `document_bytes`, using `entry`'s media size (and, for a
+/// multi-entry group, `entry` is the group's first file, used for the job title and format
+/// negotiation).
+fn submit_print_job(
+    uri: &Uri,
+    printer_attrs: &IppAttributes,
+    entry: &FileEntry,
+    file_count: usize,
+    document_bytes: &[u8],
+    pages_per_sheet: u32,
+) -> Result<JobOutcome, Box<dyn Error>> {
+    let (document_bytes, document_format) =
+        negotiate_and_finalize(printer_attrs, entry, file_count, document_bytes.to_vec())?;
 
     // Toggle this flag to switch between using raw bytes or saving to a temporary file.
     let use_file = false; // Set to true to save the PDF to "tmp.pdf" and print from file.
-    let payload = create_payload_from_pdf(&pdf_data, use_file)?;
+    let payload = create_payload_from_pdf(&document_bytes, use_file)?;
 
     // Build a media-col collection with dimensions in hundredths of a millimeter.
-    let x_dimension = (PAGE_WIDTH_MM * 100.0).round() as i32;
-    let y_dimension = (PAGE_HEIGHT_MM * 100.0).round() as i32;
+    let x_dimension = (entry.width_mm * 100.0).round() as i32;
+    let y_dimension = (entry.height_mm * 100.0).round() as i32;
     let mut media_size_map = BTreeMap::new();
     media_size_map.insert("x-dimension".to_string(), IppValue::Integer(x_dimension));
     media_size_map.insert("y-dimension".to_string(), IppValue::Integer(y_dimension));
@@ -63,12 +269,12 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Build the print job request.
     let builder = IppOperationBuilder::print_job(uri.clone(), payload)
         .user_name(env::var("USER").unwrap_or_else(|_| "noname".to_owned()))
-        .job_title(&args[2])
+        .job_title(&entry.path)
         .attribute(IppAttribute::new(
             "document-format",
-            IppValue::MimeMediaType("application/pdf".into()),
+            IppValue::MimeMediaType(document_format.into()),
         ))
-        .attribute(IppAttribute::new("media", IppValue::Keyword(DEFAULT_MEDIA.into())))
+        .attribute(IppAttribute::new("media", IppValue::Keyword(entry.media.into())))
         .attribute(IppAttribute::new("media-col", media_col))
         .attribute(IppAttribute::new(
             "print-scaling",
@@ -78,10 +284,15 @@ fn main() -> Result<(), Box<dyn Error>> {
             "print-color-mode",
             IppValue::Keyword(PRINT_COLOR_MODE.into()),
         ))
-        .attribute(IppAttribute::new("print-quality", IppValue::Enum(PRINT_QUALITY)));
-    
+        .attribute(IppAttribute::new("print-quality", IppValue::Enum(PRINT_QUALITY)))
+        // The imposition itself already happened client-side when the PDF was built
+        // (build_multi_page_pdf); the PWG Raster fallback in negotiate_and_finalize renders the
+        // image one-up. We still advertise number-up for printers/drivers that report job
+        // accounting off it.
+        .attribute(IppAttribute::new("number-up", IppValue::Integer(pages_per_sheet as i32)));
+
     let operation = builder.build();
-    let client = IppClient::new(uri);
+    let client = IppClient::new(uri.clone());
     let response = client.send(operation)?;
 
     println!("IPP status code: {}", response.header().status_code());
@@ -92,7 +303,96 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
-    Ok(())
+    match attribute_integer(response.attributes(), "job-id") {
+        Some(job_id) => poll_job_until_terminal(uri, job_id),
+        None => {
+            eprintln!("Warning: response had no job-id; can't poll job state");
+            Ok(JobOutcome::Completed)
+        }
+    }
+}
+
+/// The terminal (or timed-out) state a polled job ended up in.
+#[derive(Debug, PartialEq)]
+enum JobOutcome {
+    Completed,
+    Canceled,
+    Aborted,
+    TimedOut,
+}
+
+/// Polls Get-Job-Attributes for `job-state` (3=pending, 4=held, 5=processing, 6=stopped,
+/// 7=canceled, 8=aborted, 9=completed) every [`JOB_POLL_INTERVAL`] until the job reaches a
+/// terminal state (7/8/9) or [`JOB_POLL_TIMEOUT`] elapses, printing progress along the way.
+fn poll_job_until_terminal(uri: &Uri, job_id: i32) -> Result<JobOutcome, Box<dyn Error>> {
+    let deadline = Instant::now() + JOB_POLL_TIMEOUT;
+    loop {
+        let operation = IppOperationBuilder::get_job_attributes(uri.clone(), job_id).build();
+        let client = IppClient::new(uri.clone());
+        let response = client.send(operation)?;
+        let attrs = response.attributes();
+
+        let state = attribute_enum(attrs, "job-state").unwrap_or(5);
+        let reasons = attribute_strings(attrs, "job-state-reasons");
+        let impressions = attribute_integer(attrs, "job-impressions-completed");
+        println!(
+            "Job {} state: {}{}{}",
+            job_id,
+            job_state_name(state),
+            impressions.map(|i| format!(", {} impressions completed", i)).unwrap_or_default(),
+            if reasons.is_empty() { String::new() } else { format!(" ({})", reasons.join(", ")) },
+        );
+
+        match state {
+            9 => return Ok(JobOutcome::Completed),
+            7 => return Ok(JobOutcome::Canceled),
+            8 => return Ok(JobOutcome::Aborted),
+            _ => {}
+        }
+        if Instant::now() >= deadline {
+            eprintln!("Timed out waiting for job {} to finish", job_id);
+            return Ok(JobOutcome::TimedOut);
+        }
+        thread::sleep(JOB_POLL_INTERVAL);
+    }
+}
+
+fn job_state_name(state: i32) -> &'static str {
+    match state {
+        3 => "pending",
+        4 => "held",
+        5 => "processing",
+        6 => "stopped",
+        7 => "canceled",
+        8 => "aborted",
+        9 => "completed",
+        _ => "unknown",
+    }
+}
+
+/// Finds the first `IppValue::Enum` value of attribute `name` across all groups.
+fn attribute_enum(attrs: &IppAttributes, name: &str) -> Option<i32> {
+    attrs.groups().flat_map(|g| g.attributes()).find_map(|(n, a)| match (n == name, a.value()) {
+        (true, IppValue::Enum(v)) => Some(*v),
+        _ => None,
+    })
+}
+
+/// Finds the first `IppValue::Integer` value of attribute `name` across all groups.
+fn attribute_integer(attrs: &IppAttributes, name: &str) -> Option<i32> {
+    attrs.groups().flat_map(|g| g.attributes()).find_map(|(n, a)| match (n == name, a.value()) {
+        (true, IppValue::Integer(v)) => Some(*v),
+        _ => None,
+    })
+}
+
+/// Parses trailing `key=value` CLI arguments into a lookup map. Entries without an `=` are
+/// ignored.
+fn parse_key_value_options(args: &[String]) -> BTreeMap<String, String> {
+    args.iter()
+        .filter_map(|arg| arg.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
 }
 
 /// Creates an IPP payload from the given PDF data. If `use_file` is true, the PDF data is
@@ -125,6 +425,197 @@ fn get_printer_attributes(uri: &Uri) -> Result<IppAttributes, Box<dyn Error>> {
     Ok(response.attributes().clone())
 }
 
+/// The document format this tool decided to send, after checking what the printer advertises.
+enum NegotiatedFormat {
+    Pdf,
+    PwgRaster {
+        resolution: (u32, u32),
+        color_space: PwgColorSpace,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PwgColorSpace {
+    Srgb,
+    Sgray,
+}
+
+impl PwgColorSpace {
+    /// The `cupsColorSpace` enum value used in the PWG Raster page header (PWG5102.4).
+    fn raster_enum(self) -> u32 {
+        match self {
+            PwgColorSpace::Sgray => 18,
+            PwgColorSpace::Srgb => 19,
+        }
+    }
+
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            PwgColorSpace::Sgray => 1,
+            PwgColorSpace::Srgb => 3,
+        }
+    }
+}
+
+/// Reads `document-format-supported`, `pwg-raster-document-resolution-supported`, and
+/// `urf-supported` from the printer's attributes and decides what format to send: PDF if
+/// supported, otherwise PWG Raster at a resolution/color space the printer advertises.
+fn negotiate_document_format(attrs: &IppAttributes) -> NegotiatedFormat {
+    let formats = attribute_strings(attrs, "document-format-supported");
+    if formats.iter().any(|f| f == "application/pdf") {
+        return NegotiatedFormat::Pdf;
+    }
+
+    let resolution = attribute_strings(attrs, "pwg-raster-document-resolution-supported")
+        .iter()
+        .find_map(|s| parse_resolution(s))
+        .unwrap_or((300, 300));
+
+    // Prefer color if the printer is URF-capable (URF's "SRGB24" is near-universal); otherwise
+    // fall back to grayscale, which every PWG Raster printer is required to support.
+    let color_space = if attribute_strings(attrs, "urf-supported").iter().any(|u| u.contains("SRGB")) {
+        PwgColorSpace::Srgb
+    } else {
+        PwgColorSpace::Sgray
+    };
+
+    NegotiatedFormat::PwgRaster { resolution, color_space }
+}
+
+/// Parses a resolution string like `"300x300"` or `"300"` into `(x_dpi, y_dpi)`.
+fn parse_resolution(s: &str) -> Option<(u32, u32)> {
+    match s.split_once('x') {
+        Some((x, y)) => Some((x.trim().parse().ok()?, y.trim().parse().ok()?)),
+        None => {
+            let dpi = s.trim().parse().ok()?;
+            Some((dpi, dpi))
+        }
+    }
+}
+
+/// Collects every value of attribute `name` across all groups as strings, unwrapping `Array`s.
+fn attribute_strings(attrs: &IppAttributes, name: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for group in attrs.groups() {
+        for (attr_name, attribute) in group.attributes() {
+            if attr_name != name {
+                continue;
+            }
+            collect_ipp_value_strings(attribute.value(), &mut out);
+        }
+    }
+    out
+}
+
+fn collect_ipp_value_strings(value: &IppValue, out: &mut Vec<String>) {
+    match value {
+        IppValue::Array(values) => {
+            for v in values {
+                collect_ipp_value_strings(v, out);
+            }
+        }
+        IppValue::MimeMediaType(s) | IppValue::Keyword(s) | IppValue::TextWithoutLanguage(s) => {
+            out.push(s.clone())
+        }
+        other => out.push(format!("{:?}", other)),
+    }
+}
+
+/// Rasterizes an image file to a single-page PWG Raster stream: a big-endian `RaS2` sync word,
+/// a 1796-byte page header (a practical subset of the PWG5102.4 fields; unused fields are left
+/// zeroed), and the bitmap packed as lines, each starting with a line-repeat-count byte (0 =
+/// print once; used to collapse identical adjacent rows) followed by the row's pixels RLE-encoded
+/// as repeat-count-byte + pixel-group runs (PWG5102.4 §Compression).
+fn rasterize_image_to_pwg(
+    image_path: &str,
+    resolution: (u32, u32),
+    color_space: PwgColorSpace,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let width = (PAGE_WIDTH_MM / 25.4 * resolution.0 as f32).round() as u32;
+    let height = (PAGE_HEIGHT_MM / 25.4 * resolution.1 as f32).round() as u32;
+
+    let img = ImageReader::open(image_path)?.decode()?;
+    let resized = img.resize_exact(width, height, ::image::imageops::FilterType::Lanczos3);
+
+    let bytes_per_pixel = color_space.bytes_per_pixel();
+    let pixels: Vec<u8> = match color_space {
+        PwgColorSpace::Srgb => resized.to_rgb8().into_raw(),
+        PwgColorSpace::Sgray => resized.to_luma8().into_raw(),
+    };
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"RaS2");
+    out.extend_from_slice(&pwg_raster_page_header(width, height, resolution, color_space));
+
+    let bytes_per_line = width as usize * bytes_per_pixel;
+    let rows: Vec<&[u8]> = pixels.chunks_exact(bytes_per_line).collect();
+    let mut row_index = 0;
+    while row_index < rows.len() {
+        let row = rows[row_index];
+        let mut repeat = 1;
+        while repeat < 256 && row_index + repeat < rows.len() && rows[row_index + repeat] == row {
+            repeat += 1;
+        }
+        // Every PWG Raster line starts with a line-repeat-count byte (0 = print once) before the
+        // pixel-run groups; collapsing identical adjacent rows into it also shrinks solid areas.
+        out.push((repeat - 1) as u8);
+        out.extend_from_slice(&rle_encode_row(row, bytes_per_pixel));
+        row_index += repeat;
+    }
+
+    Ok(out)
+}
+
+/// Builds a 1796-byte PWG Raster page header with the fields this tool relies on set; everything
+/// else is zeroed, matching a printer-agnostic subset of the `cups`/PWG raster header layout.
+fn pwg_raster_page_header(
+    width: u32,
+    height: u32,
+    resolution: (u32, u32),
+    color_space: PwgColorSpace,
+) -> [u8; 1796] {
+    let mut header = [0u8; 1796];
+
+    let media_type = b"photographic-glossy";
+    header[128..128 + media_type.len()].copy_from_slice(media_type);
+
+    header[276..280].copy_from_slice(&resolution.0.to_be_bytes());
+    header[280..284].copy_from_slice(&resolution.1.to_be_bytes());
+
+    header[372..376].copy_from_slice(&width.to_be_bytes());
+    header[376..380].copy_from_slice(&height.to_be_bytes());
+    header[384..388].copy_from_slice(&8u32.to_be_bytes()); // cupsBitsPerColor
+    header[388..392].copy_from_slice(&((color_space.bytes_per_pixel() as u32) * 8).to_be_bytes()); // cupsBitsPerPixel
+    header[392..396].copy_from_slice(&((width as usize * color_space.bytes_per_pixel()) as u32).to_be_bytes()); // cupsBytesPerLine
+    header[396..400].copy_from_slice(&0u32.to_be_bytes()); // cupsColorOrder: 0 = chunky
+    header[400..404].copy_from_slice(&color_space.raster_enum().to_be_bytes());
+
+    header
+}
+
+/// RLE-encodes one row's pixel-run groups (the part of a PWG Raster line that follows the
+/// line-repeat-count byte): runs of identical pixels, each a control byte holding
+/// `run_length - 1` (0–127) followed by a single pixel group.
+fn rle_encode_row(row: &[u8], bytes_per_pixel: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    let num_pixels = row.len() / bytes_per_pixel;
+    let mut i = 0;
+    while i < num_pixels {
+        let pixel = &row[i * bytes_per_pixel..(i + 1) * bytes_per_pixel];
+        let mut run = 1;
+        while run < 128
+            && i + run < num_pixels
+            && &row[(i + run) * bytes_per_pixel..(i + run + 1) * bytes_per_pixel] == pixel
+        {
+            run += 1;
+        }
+        out.push((run - 1) as u8);
+        out.extend_from_slice(pixel);
+        i += run;
+    }
+    out
+}
+
 /// Prints printer attributes for debugging.
 fn debug_print_printer_attributes(attrs: &IppAttributes) {
     for group in attrs.groups() {
@@ -135,47 +626,1317 @@ fn debug_print_printer_attributes(attrs: &IppAttributes) {
     }
 }
 
-/// Converts an image file to a PDF with a page size of 101.6 × 152.4 mm (4×6 inches).
-fn convert_image_to_pdf(image_path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
-    // Open and decode the image.
-    let img = ImageReader::open(image_path)?.decode()?;
-    // Create a PDF document with the 4×6-inch page size.
-    let (doc, page1, layer1) = PdfDocument::new(
-        "Image Print Job",
-        Mm(PAGE_WIDTH_MM),
-        Mm(PAGE_HEIGHT_MM),
-        "Layer 1",
-    );
-    let current_layer = doc.get_page(page1).get_layer(layer1);
-
-    // For simplicity, add the image without scaling adjustments.
-    let rgb_image = img.to_rgb8();
-    let image = ImageXObject {
-        width: Px(img.width() as usize),
-        height: Px(img.height() as usize),
-        color_space: ColorSpace::Rgb,
-        bits_per_component: ColorBits::Bit8,
-        interpolate: true,
-        image_data: rgb_image.into_raw(),
-        image_filter: None,
-        clipping_bbox: None,
-        smask: None,
+/// Converts a single image file to a one-page PDF sized 101.6 × 152.4 mm (4×6 inches). If
+/// `pages_per_sheet` is greater than 1, the image is repeated N-up on that one sheet. This is the
+/// one-entry case of [`build_multi_page_pdf`].
+fn convert_image_to_pdf(image_path: &str, pages_per_sheet: u32) -> Result<Vec<u8>, Box<dyn Error>> {
+    build_multi_page_pdf(
+        &[(PageContent::Image(image_path.to_string()), PAGE_WIDTH_MM, PAGE_HEIGHT_MM)],
+        pages_per_sheet,
+    )
+}
+
+/// What one output page of [`build_multi_page_pdf`] renders.
+enum PageContent {
+    /// A single image path, repeated `pages_per_sheet` times in an N-up grid on the page.
+    Image(String),
+    /// Distinct image paths, one per cell of an N-up grid on the page — multiple *source* pages
+    /// composed onto one output sheet, as opposed to [`PageContent::Image`]'s repeated copies.
+    ImposedImages(Vec<String>),
+    Svg(String),
+}
+
+/// Builds a single multi-page PDF from `(content, page_width_mm, page_height_mm)` entries, one
+/// output page per entry, each sized independently — e.g. a 4×6 photo page followed by an A4
+/// document page in the same PDF.
+fn build_multi_page_pdf(
+    pages: &[(PageContent, f32, f32)],
+    pages_per_sheet: u32,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (first_content, first_w, first_h) = &pages[0];
+    let (doc, page1, layer1) = PdfDocument::new("Image Print Job", Mm(*first_w), Mm(*first_h), "Layer 1");
+    add_page_content(first_content, doc.get_page(page1).get_layer(layer1), *first_w, *first_h, pages_per_sheet)?;
+
+    for (content, width_mm, height_mm) in &pages[1..] {
+        let (page, layer) = doc.add_page(Mm(*width_mm), Mm(*height_mm), "Layer 1");
+        add_page_content(content, doc.get_page(page).get_layer(layer), *width_mm, *height_mm, pages_per_sheet)?;
+    }
+
+    let mut buffer = Vec::new();
+    doc.save(&mut BufWriter::new(Cursor::new(&mut buffer)))?;
+    Ok(buffer)
+}
+
+fn add_page_content(
+    content: &PageContent,
+    layer: PdfLayerReference,
+    width_mm: f32,
+    height_mm: f32,
+    pages_per_sheet: u32,
+) -> Result<(), Box<dyn Error>> {
+    match content {
+        PageContent::Image(path) => add_image_page(layer, width_mm, height_mm, path, pages_per_sheet),
+        PageContent::ImposedImages(paths) => add_imposed_page(layer, width_mm, height_mm, paths, pages_per_sheet),
+        PageContent::Svg(path) => add_svg_page(layer, width_mm, height_mm, path),
+    }
+}
+
+/// One decoded, EXIF-oriented image ready to be placed into an N-up grid cell.
+struct PreparedImage {
+    image: Image,
+    native_width_px: usize,
+    native_height_px: usize,
+    transform: OrientationTransform,
+}
+
+/// Decodes `image_path` (JPEG fast path via [`jpeg_xobject`], generic decode otherwise) and reads
+/// its EXIF orientation, ready to be placed on a page by [`place_prepared_image`].
+fn prepare_image_for_page(image_path: &str) -> Result<PreparedImage, Box<dyn Error>> {
+    let raw_bytes = fs::read(image_path)?;
+    let (xobject, native_width_px, native_height_px) = if let Some(jpeg) = jpeg_xobject(&raw_bytes) {
+        println!("Embedding JPEG verbatim via DCTDecode ({}x{})", jpeg.width.0, jpeg.height.0);
+        let (w, h) = (jpeg.width.0, jpeg.height.0);
+        (jpeg, w, h)
+    } else {
+        // Not a JPEG, a progressive/CMYK JPEG jpeg_xobject declined to embed verbatim, or its SOF
+        // marker couldn't be parsed: fall back to decoding to raw RGB.
+        warn_if_jpeg_needs_reencode(&raw_bytes);
+        let img = ImageReader::open(image_path)?.decode()?;
+        let rgb_image = img.to_rgb8();
+        let (w, h) = (img.width() as usize, img.height() as usize);
+        (
+            ImageXObject {
+                width: Px(w),
+                height: Px(h),
+                color_space: ColorSpace::Rgb,
+                bits_per_component: ColorBits::Bit8,
+                interpolate: true,
+                image_data: rgb_image.into_raw(),
+                image_filter: None,
+                clipping_bbox: None,
+                smask: None,
+            },
+            w,
+            h,
+        )
     };
 
-    let image_layer = Image::from(image);
-    image_layer.add_to_layer(
-        current_layer,
+    let orientation = read_exif_orientation(&raw_bytes);
+    let transform = orientation_transform(orientation);
+    println!("Detected EXIF orientation: {} ({})", orientation, transform.description);
+
+    Ok(PreparedImage { image: Image::from(xobject), native_width_px, native_height_px, transform })
+}
+
+/// Places `prepared` into the `(row, col)` cell of a `rows`×`cols` grid on a `width_mm` ×
+/// `height_mm` page, scaled to fit the cell and rotated/mirrored about its own center per its
+/// EXIF orientation.
+fn place_prepared_image(
+    current_layer: &PdfLayerReference,
+    prepared: &PreparedImage,
+    width_mm: f32,
+    height_mm: f32,
+    rows: u32,
+    cols: u32,
+    row: u32,
+    col: u32,
+) {
+    // Dimensions as laid out on the page after rotation, in mm, assuming the 300 dpi we pass to
+    // ImageTransform below.
+    let (upright_width_px, upright_height_px) = if prepared.transform.swap_dimensions {
+        (prepared.native_height_px, prepared.native_width_px)
+    } else {
+        (prepared.native_width_px, prepared.native_height_px)
+    };
+    let native_width_mm = upright_width_px as f32 / 300.0 * 25.4;
+    let native_height_mm = upright_height_px as f32 / 300.0 * 25.4;
+
+    let cell_width_mm = width_mm / cols as f32;
+    let cell_height_mm = height_mm / rows as f32;
+    let scale = (cell_width_mm / native_width_mm).min(cell_height_mm / native_height_mm);
+
+    // `ImageRotation` pivots about a point in the *source* image's own pixel space (pre-scale),
+    // so use its center rather than the Px(0) corner — otherwise rotating/mirroring shifts the
+    // content out of its cell instead of turning it in place.
+    let rotate = prepared.transform.rotate_ccw_degrees.map(|angle_ccw_degrees| ImageRotation {
+        angle_ccw_degrees,
+        rotation_center_x: Px(prepared.native_width_px / 2),
+        rotation_center_y: Px(prepared.native_height_px / 2),
+    });
+    // Because rotation pivots about that center, the center's position is unaffected by the
+    // rotation itself — only by scale/mirror and the final translate. So translate is solved to
+    // land that (scaled, possibly mirrored) center on the target cell's center, which keeps the
+    // image inside its cell for every orientation.
+    let center_local_x_mm = (prepared.native_width_px as f32 / 300.0 * 25.4 / 2.0) * scale * prepared.transform.mirror_h_scale;
+    let center_local_y_mm = (prepared.native_height_px as f32 / 300.0 * 25.4 / 2.0) * scale * prepared.transform.mirror_v_scale;
+
+    // Cells are ordered left-to-right, top-to-bottom; PDF coordinates are bottom-up, so the first
+    // row sits at the top of the page.
+    let cell_x = col as f32 * cell_width_mm;
+    let cell_y = height_mm - (row as f32 + 1.0) * cell_height_mm;
+    let target_center_x = cell_x + cell_width_mm / 2.0;
+    let target_center_y = cell_y + cell_height_mm / 2.0;
+    let translate_x = target_center_x - center_local_x_mm;
+    let translate_y = target_center_y - center_local_y_mm;
+
+    prepared.image.clone().add_to_layer(
+        current_layer.clone(),
         ImageTransform {
-            translate_x: Some(Mm(0.0)),
-            translate_y: Some(Mm(0.0)),
-            rotate: None,
-            scale_x: Some(1.0),
-            scale_y: Some(1.0),
+            translate_x: Some(Mm(translate_x)),
+            translate_y: Some(Mm(translate_y)),
+            rotate,
+            scale_x: Some(scale * prepared.transform.mirror_h_scale),
+            scale_y: Some(scale * prepared.transform.mirror_v_scale),
             dpi: Some(300.0),
         },
     );
+}
+
+/// Places one image onto `layer` of a `width_mm` × `height_mm` page, repeated `pages_per_sheet`
+/// times across an N-up grid (the single-source "repeated copies" imposition case).
+fn add_image_page(
+    current_layer: PdfLayerReference,
+    width_mm: f32,
+    height_mm: f32,
+    image_path: &str,
+    pages_per_sheet: u32,
+) -> Result<(), Box<dyn Error>> {
+    let prepared = prepare_image_for_page(image_path)?;
+    let (rows, cols) = imposition_grid(pages_per_sheet);
+    println!("Imposing {} page(s) per sheet on a {}x{} grid", pages_per_sheet, rows, cols);
+
+    for index in 0..pages_per_sheet {
+        let row = index / cols;
+        let col = index % cols;
+        if row >= rows {
+            break;
+        }
+        place_prepared_image(&current_layer, &prepared, width_mm, height_mm, rows, cols, row, col);
+    }
+
+    Ok(())
+}
+
+/// Places distinct `image_paths`, one per cell, onto `layer` of a `width_mm` × `height_mm` page
+/// laid out on an N-up grid sized for `pages_per_sheet` — multiple *source* pages composed onto
+/// one output sheet, the multi-file counterpart to [`add_image_page`]'s repeated-copies case.
+fn add_imposed_page(
+    current_layer: PdfLayerReference,
+    width_mm: f32,
+    height_mm: f32,
+    image_paths: &[String],
+    pages_per_sheet: u32,
+) -> Result<(), Box<dyn Error>> {
+    let (rows, cols) = imposition_grid(pages_per_sheet);
+    println!("Composing {} source page(s) onto one {}x{} sheet", image_paths.len(), rows, cols);
+
+    for (index, path) in image_paths.iter().enumerate() {
+        let row = index as u32 / cols;
+        let col = index as u32 % cols;
+        if row >= rows {
+            break;
+        }
+        let prepared = prepare_image_for_page(path)?;
+        place_prepared_image(&current_layer, &prepared, width_mm, height_mm, rows, cols, row, col);
+    }
+
+    Ok(())
+}
+
+/// Computes the rows×cols grid for `n` pages per sheet: N=2 is a 1×2 landscape strip, otherwise
+/// the near-square factorization (rows = ceil(sqrt(n)), cols = ceil(n / rows)).
+fn imposition_grid(n: u32) -> (u32, u32) {
+    let n = n.max(1);
+    if n == 2 {
+        return (1, 2);
+    }
+    let rows = (n as f32).sqrt().ceil() as u32;
+    let cols = (n + rows - 1) / rows;
+    (rows, cols)
+}
+
+/// The placement adjustments needed to display an image with a given EXIF orientation upright.
+struct OrientationTransform {
+    /// Degrees counter-clockwise to rotate, pivoting about the image's own center — the caller
+    /// builds the actual [`ImageRotation`] once it knows the source image's pixel dimensions.
+    rotate_ccw_degrees: Option<f32>,
+    mirror_h_scale: f32,
+    mirror_v_scale: f32,
+    /// True for orientations 5–8, where the image is rotated 90°/270° and width/height must be
+    /// swapped for downstream page-fit logic to lay it out correctly.
+    swap_dimensions: bool,
+    description: &'static str,
+}
+
+/// Maps an EXIF `Orientation` value (1–8) to the rotation/mirror needed to display the image
+/// upright. Unknown values are treated as 1 (no transform).
+fn orientation_transform(orientation: u16) -> OrientationTransform {
+    match orientation {
+        2 => OrientationTransform {
+            rotate_ccw_degrees: None,
+            mirror_h_scale: -1.0,
+            mirror_v_scale: 1.0,
+            swap_dimensions: false,
+            description: "mirrored horizontally",
+        },
+        3 => OrientationTransform {
+            rotate_ccw_degrees: Some(180.0),
+            mirror_h_scale: 1.0,
+            mirror_v_scale: 1.0,
+            swap_dimensions: false,
+            description: "rotated 180°",
+        },
+        4 => OrientationTransform {
+            rotate_ccw_degrees: None,
+            mirror_h_scale: 1.0,
+            mirror_v_scale: -1.0,
+            swap_dimensions: false,
+            description: "mirrored vertically",
+        },
+        5 => OrientationTransform {
+            rotate_ccw_degrees: Some(90.0),
+            mirror_h_scale: -1.0,
+            mirror_v_scale: 1.0,
+            swap_dimensions: true,
+            description: "rotated 90° CCW and mirrored horizontally",
+        },
+        6 => OrientationTransform {
+            rotate_ccw_degrees: Some(270.0),
+            mirror_h_scale: 1.0,
+            mirror_v_scale: 1.0,
+            swap_dimensions: true,
+            description: "rotated 90° CW",
+        },
+        7 => OrientationTransform {
+            rotate_ccw_degrees: Some(270.0),
+            mirror_h_scale: -1.0,
+            mirror_v_scale: 1.0,
+            swap_dimensions: true,
+            description: "rotated 90° CW and mirrored horizontally",
+        },
+        8 => OrientationTransform {
+            rotate_ccw_degrees: Some(90.0),
+            mirror_h_scale: 1.0,
+            mirror_v_scale: 1.0,
+            swap_dimensions: true,
+            description: "rotated 90° CCW",
+        },
+        _ => OrientationTransform {
+            rotate_ccw_degrees: None,
+            mirror_h_scale: 1.0,
+            mirror_v_scale: 1.0,
+            swap_dimensions: false,
+            description: "normal",
+        },
+    }
+}
+
+/// Reads the EXIF `Orientation` tag (values 1–8) from a JPEG's APP1 segment. Returns 1 (normal)
+/// if the file has no EXIF data, no `Orientation` tag, or isn't a JPEG at all.
+fn read_exif_orientation(data: &[u8]) -> u16 {
+    parse_exif_orientation(data).unwrap_or(1)
+}
+
+fn parse_exif_orientation(data: &[u8]) -> Option<u16> {
+    if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+        return None;
+    }
+
+    // Find the APP1 segment (marker 0xFFE1) whose payload starts with "Exif\0\0".
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            pos += 1;
+            continue;
+        }
+        let marker = data[pos + 1];
+        if marker == 0xD9 || marker == 0xDA {
+            return None; // EOI / start of scan: no APP1 found before image data.
+        }
+        let segment_len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let payload = pos + 4;
+        if marker == 0xE1 && payload + 6 <= data.len() && &data[payload..payload + 6] == b"Exif\0\0" {
+            return parse_tiff_orientation(&data[payload + 6..]);
+        }
+        pos += 2 + segment_len;
+    }
+    None
+}
+
+/// Parses a TIFF/EXIF IFD0 looking for tag `0x0112` (Orientation).
+fn parse_tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    if tiff.len() < 8 {
+        return None;
+    }
+    let little_endian = match &tiff[0..2] {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let read_u16 = |b: &[u8]| if little_endian { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) };
+    let read_u32 = |b: &[u8]| {
+        if little_endian {
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        } else {
+            u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+        }
+    };
+
+    let ifd0_offset = read_u32(&tiff[4..8]) as usize;
+    if ifd0_offset + 2 > tiff.len() {
+        return None;
+    }
+    let entry_count = read_u16(&tiff[ifd0_offset..ifd0_offset + 2]) as usize;
+    let mut entry_pos = ifd0_offset + 2;
+    for _ in 0..entry_count {
+        if entry_pos + 12 > tiff.len() {
+            break;
+        }
+        let tag = read_u16(&tiff[entry_pos..entry_pos + 2]);
+        if tag == 0x0112 {
+            // SHORT values are stored inline in the first 2 bytes of the 4-byte value field.
+            return Some(read_u16(&tiff[entry_pos + 8..entry_pos + 10]));
+        }
+        entry_pos += 12;
+    }
+    None
+}
+
+/// Warns on stderr when `data` is a JPEG that [`jpeg_xobject`] declines to embed verbatim
+/// (progressive SOF2, or CMYK) so the re-encode fallback's cost — a decode/re-encode round trip,
+/// and for CMYK a conversion to RGB since this tool has no way to emit the `/Decode [1 0 1 0 1 0
+/// 1 0]` array `printpdf::ImageXObject` would need to embed it color-correct — is visible to
+/// whoever is running the tool, not just to someone reading this source file.
+fn warn_if_jpeg_needs_reencode(data: &[u8]) {
+    if let Some((_, _, components, is_baseline)) = parse_jpeg_sof(data) {
+        if !is_baseline {
+            eprintln!("Warning: progressive JPEG can't be embedded verbatim; re-encoding");
+        } else if components == 4 {
+            eprintln!("Warning: CMYK JPEG can't be embedded verbatim (no /Decode array support); re-encoding as RGB");
+        }
+    }
+}
+
+/// Builds an `ImageXObject` that embeds `data` verbatim as `/Filter /DCTDecode`, skipping the
+/// decode/re-encode round trip entirely. Restricted to baseline (SOF0), non-CMYK JPEGs: PDF's
+/// `/DCTDecode` filter is specified against baseline JPEG, so progressive (SOF2) frames embedded
+/// verbatim fail to decode on many RIPs; and this tool has no way to emit the `/Decode [1 0 1 0 1
+/// 0 1 0]` array Adobe-inverted CMYK JPEGs need, so those are left for the caller to re-encode via
+/// the generic decode path instead of embedding them color-inverted. Returns `None` if `data`
+/// isn't a JPEG, its SOF marker can't be parsed, or it falls into one of those excluded cases —
+/// call [`warn_if_jpeg_needs_reencode`] first to surface that last case to the user.
+fn jpeg_xobject(data: &[u8]) -> Option<ImageXObject> {
+    let (width, height, components, is_baseline) = parse_jpeg_sof(data)?;
+    if !is_baseline {
+        return None;
+    }
+    let color_space = match components {
+        1 => ColorSpace::Greyscale,
+        3 => ColorSpace::Rgb,
+        _ => return None, // CMYK (4): needs a /Decode array this tool can't express; re-encode instead.
+    };
+
+    Some(ImageXObject {
+        width: Px(width as usize),
+        height: Px(height as usize),
+        color_space,
+        bits_per_component: ColorBits::Bit8,
+        interpolate: true,
+        image_data: data.to_vec(),
+        image_filter: Some(ImageFilter::DCT),
+        clipping_bbox: None,
+        smask: None,
+    })
+}
+
+/// Scans a JPEG byte stream for its SOF0/SOF2 frame header (marker `0xFFC0`/`0xFFC2`) and returns
+/// `(width, height, components, is_baseline)`, where `is_baseline` is true only for SOF0. Returns
+/// `None` if the file isn't a JPEG or has no SOF marker.
+fn parse_jpeg_sof(data: &[u8]) -> Option<(u32, u32, u8, bool)> {
+    if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+        return None; // Not a JPEG (missing SOI marker).
+    }
+
+    let mut pos = 2;
+    while pos + 1 < data.len() {
+        if data[pos] != 0xFF {
+            pos += 1;
+            continue;
+        }
+        let marker = data[pos + 1];
+        pos += 2;
+
+        // Markers with no payload.
+        if marker == 0x01 || (0xD0..=0xD9).contains(&marker) {
+            if marker == 0xD9 {
+                break; // EOI
+            }
+            continue;
+        }
+        if pos + 1 >= data.len() {
+            break;
+        }
+        let segment_len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
+        let payload = pos + 2;
+
+        // SOF0 (baseline) and SOF2 (progressive) share the same header layout; skip the other
+        // SOFn variants (arithmetic coding, etc.) that this tool doesn't expect to see.
+        if marker == 0xC0 || marker == 0xC2 {
+            if payload + 6 > data.len() {
+                return None;
+            }
+            let height = u16::from_be_bytes([data[payload + 1], data[payload + 2]]) as u32;
+            let width = u16::from_be_bytes([data[payload + 3], data[payload + 4]]) as u32;
+            let components = data[payload + 5];
+            return Some((width, height, components, marker == 0xC0));
+        }
+
+        pos += segment_len;
+    }
+
+    None
+}
+
+/// Converts a single SVG file to a one-page vector PDF.
+fn convert_svg_to_pdf(svg_path: &str, width_mm: f32, height_mm: f32) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (doc, page1, layer1) = PdfDocument::new("SVG Print Job", Mm(width_mm), Mm(height_mm), "Layer 1");
+    add_svg_page(doc.get_page(page1).get_layer(layer1), width_mm, height_mm, svg_path)?;
 
     let mut buffer = Vec::new();
     doc.save(&mut BufWriter::new(Cursor::new(&mut buffer)))?;
     Ok(buffer)
 }
+
+/// Parses `svg_path` and draws its shapes directly as PDF vector content onto `layer`, mapping
+/// the SVG's viewBox to `width_mm` × `height_mm` with an aspect-preserving transform centered on
+/// the page. `<image>` elements (the one genuinely raster construct SVG can contain) are decoded
+/// and embedded as an image XObject positioned/stretched to their `x`/`y`/`width`/`height` box.
+/// Unsupported elements (gradients, filters, masks, patterns) are not rasterized: they are left
+/// out of the PDF, with a warning naming them. Paint-server references this tool can't resolve (gradients, filters, masks, patterns) fall
+/// back to a flat black fill/stroke rather than being dropped, so shapes stay visible.
+fn add_svg_page(layer: PdfLayerReference, width_mm: f32, height_mm: f32, svg_path: &str) -> Result<(), Box<dyn Error>> {
+    let svg_text = fs::read_to_string(svg_path)?;
+    let view_box = parse_svg_view_box(&svg_text);
+    let scale = (width_mm / view_box.width).min(height_mm / view_box.height);
+    let offset_x = (width_mm - view_box.width * scale) / 2.0;
+    let offset_y = (height_mm - view_box.height * scale) / 2.0;
+
+    // Maps an SVG-space point to a page-space Mm point: scale, flip Y (SVG is top-down, PDF is
+    // bottom-up), and center within the page.
+    let to_page = |x: f32, y: f32| -> Point {
+        let px = offset_x + (x - view_box.min_x) * scale;
+        let py = height_mm - (offset_y + (y - view_box.min_y) * scale);
+        Point::new(Mm(px), Mm(py))
+    };
+
+    let mut unsupported = Vec::new();
+    for element in parse_svg_elements(&svg_text) {
+        match element {
+            SvgElement::Rect { x, y, width, height, fill } => {
+                let points = vec![
+                    (to_page(x, y), false),
+                    (to_page(x + width, y), false),
+                    (to_page(x + width, y + height), false),
+                    (to_page(x, y + height), false),
+                ];
+                draw_polygon(&layer, points, fill);
+            }
+            SvgElement::Circle { cx, cy, r, fill } => {
+                // Approximate the circle with an 8-point closed polygon in SVG space, then map
+                // each point through the same viewBox transform as everything else.
+                let points = (0..8)
+                    .map(|i| {
+                        let angle = (i as f32) * std::f32::consts::PI / 4.0;
+                        (to_page(cx + r * angle.cos(), cy + r * angle.sin()), false)
+                    })
+                    .collect();
+                draw_polygon(&layer, points, fill);
+            }
+            SvgElement::Line { x1, y1, x2, y2 } => {
+                layer.set_outline_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+                layer.add_shape(Line {
+                    points: vec![(to_page(x1, y1), false), (to_page(x2, y2), false)],
+                    is_closed: false,
+                });
+            }
+            SvgElement::Path { commands, fill } => {
+                // A path's `d` can hold several subpaths (one per `M`/`m`); each is its own shape,
+                // not one continuous outline connecting them all.
+                for subpath in split_svg_subpaths(&commands) {
+                    let points: Vec<(Point, bool)> = subpath
+                        .commands
+                        .iter()
+                        .map(|cmd| match cmd {
+                            PathCommand::MoveTo(x, y) | PathCommand::LineTo(x, y) => (to_page(*x, *y), false),
+                            PathCommand::CurveControl(x, y) => (to_page(*x, *y), true),
+                            PathCommand::ClosePath => unreachable!("ClosePath is consumed by split_svg_subpaths"),
+                        })
+                        .collect();
+                    if points.is_empty() {
+                        continue;
+                    }
+                    match fill {
+                        // Fill implicitly closes the region regardless of an explicit `Z`.
+                        Some((r, g, b)) => {
+                            layer.set_fill_color(Color::Rgb(Rgb::new(r, g, b, None)));
+                            layer.add_shape(Line { points, is_closed: true });
+                        }
+                        // No fill: draw only the outline, open unless the subpath had an
+                        // explicit `Z`.
+                        None => {
+                            layer.set_outline_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+                            layer.add_shape(Line { points, is_closed: subpath.closed });
+                        }
+                    }
+                }
+            }
+            SvgElement::Image { x, y, width, height, href } => {
+                if href.is_empty() {
+                    unsupported.push("image (no href)".to_string());
+                    continue;
+                }
+                match load_svg_image_xobject(svg_path, &href) {
+                    Ok((xobject, native_width_px, native_height_px)) => {
+                        // <image> stretches to its x/y/width/height box (we ignore
+                        // preserveAspectRatio), so scale each axis independently to fit it.
+                        let box_width_mm = width * scale;
+                        let box_height_mm = height * scale;
+                        let box_x_mm = offset_x + (x - view_box.min_x) * scale;
+                        let box_top_mm = offset_y + (y - view_box.min_y) * scale;
+                        let box_y_mm = height_mm - box_top_mm - box_height_mm;
+                        let scale_x = box_width_mm / (native_width_px as f32 / 300.0 * 25.4);
+                        let scale_y = box_height_mm / (native_height_px as f32 / 300.0 * 25.4);
+                        Image::from(xobject).add_to_layer(
+                            layer.clone(),
+                            ImageTransform {
+                                translate_x: Some(Mm(box_x_mm)),
+                                translate_y: Some(Mm(box_y_mm)),
+                                rotate: None,
+                                scale_x: Some(scale_x),
+                                scale_y: Some(scale_y),
+                                dpi: Some(300.0),
+                            },
+                        );
+                    }
+                    Err(err) => {
+                        eprintln!("Warning: couldn't embed SVG <image> '{}': {}", href, err);
+                        unsupported.push(format!("image ({})", href));
+                    }
+                }
+            }
+            SvgElement::Unsupported(tag) => unsupported.push(tag),
+        }
+    }
+
+    if !unsupported.is_empty() {
+        eprintln!(
+            "Warning: SVG '{}' has unsupported element(s) [{}]; they were left out of the PDF",
+            svg_path,
+            unsupported.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Decodes an SVG `<image>` element's `href` (a `data:` URI or a relative/absolute file path) into
+/// an embeddable image XObject, trying the JPEG fast path before falling back to a generic decode.
+fn load_svg_image_xobject(svg_path: &str, href: &str) -> Result<(ImageXObject, usize, usize), Box<dyn Error>> {
+    let bytes = if let Some(rest) = href.strip_prefix("data:") {
+        let comma = rest.find(',').ok_or("malformed data URI")?;
+        if !rest[..comma].ends_with(";base64") {
+            return Err("only base64 data URIs are supported".into());
+        }
+        base64_decode(&rest[comma + 1..])?
+    } else {
+        fs::read(resolve_svg_asset_path(svg_path, href))?
+    };
+
+    if let Some(jpeg) = jpeg_xobject(&bytes) {
+        let (w, h) = (jpeg.width.0, jpeg.height.0);
+        return Ok((jpeg, w, h));
+    }
+
+    warn_if_jpeg_needs_reencode(&bytes);
+    let decoded = ::image::load_from_memory(&bytes)?;
+    let (w, h) = (decoded.width() as usize, decoded.height() as usize);
+    Ok((
+        ImageXObject {
+            width: Px(w),
+            height: Px(h),
+            color_space: ColorSpace::Rgb,
+            bits_per_component: ColorBits::Bit8,
+            interpolate: true,
+            image_data: decoded.to_rgb8().into_raw(),
+            image_filter: None,
+            clipping_bbox: None,
+            smask: None,
+        },
+        w,
+        h,
+    ))
+}
+
+/// Resolves a non-`data:` `href` relative to the SVG file's own directory, matching how browsers
+/// resolve relative `<image>` references.
+fn resolve_svg_asset_path(svg_path: &str, href: &str) -> String {
+    if Path::new(href).is_absolute() {
+        return href.to_string();
+    }
+    match Path::new(svg_path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.join(href).to_string_lossy().into_owned(),
+        _ => href.to_string(),
+    }
+}
+
+/// Minimal standard-alphabet base64 decoder for `data:` URI `<image>` hrefs (no external crate
+/// available in this tree).
+fn base64_decode(input: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut lookup = [255u8; 256];
+    for (i, &c) in ALPHABET.iter().enumerate() {
+        lookup[c as usize] = i as u8;
+    }
+
+    let clean: Vec<u8> = input.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    let mut out = Vec::with_capacity(clean.len() / 4 * 3);
+    for chunk in clean.chunks(4) {
+        let mut vals = [0u8; 4];
+        let mut pad = 0;
+        for (i, &b) in chunk.iter().enumerate() {
+            if b == b'=' {
+                pad += 1;
+            } else {
+                let v = lookup[b as usize];
+                if v == 255 {
+                    return Err(format!("invalid base64 byte '{}'", b as char).into());
+                }
+                vals[i] = v;
+            }
+        }
+        let n = (vals[0] as u32) << 18 | (vals[1] as u32) << 12 | (vals[2] as u32) << 6 | (vals[3] as u32);
+        out.push((n >> 16) as u8);
+        if pad < 2 {
+            out.push((n >> 8) as u8);
+        }
+        if pad < 1 {
+            out.push(n as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn draw_polygon(layer: &PdfLayerReference, points: Vec<(Point, bool)>, fill: Option<(f32, f32, f32)>) {
+    if let Some((r, g, b)) = fill {
+        layer.set_fill_color(Color::Rgb(Rgb::new(r, g, b, None)));
+    }
+    layer.add_shape(Line { points, is_closed: true });
+}
+
+/// The SVG `viewBox`, in SVG user units.
+struct ViewBox {
+    min_x: f32,
+    min_y: f32,
+    width: f32,
+    height: f32,
+}
+
+/// Reads the `viewBox="min-x min-y width height"` attribute off the root `<svg>` tag. Falls back
+/// to a 100×100 unit square if there's no viewBox (e.g. only `width`/`height` are given).
+fn parse_svg_view_box(svg_text: &str) -> ViewBox {
+    if let Some(value) = svg_attribute(svg_text, "viewBox") {
+        let parts: Vec<f32> = value.split_whitespace().filter_map(|p| p.parse().ok()).collect();
+        if parts.len() == 4 {
+            return ViewBox { min_x: parts[0], min_y: parts[1], width: parts[2], height: parts[3] };
+        }
+    }
+    ViewBox { min_x: 0.0, min_y: 0.0, width: 100.0, height: 100.0 }
+}
+
+/// Finds `name="..."` in `text` and returns the attribute value, requiring a word boundary
+/// (whitespace, `<`, or start-of-text) right before `name` so `svg_attribute(tag, "x")` doesn't
+/// match inside `rx="..."`/`cx="..."`, and `svg_attribute(tag, "width")` doesn't match inside
+/// `stroke-width="..."`. Good enough for the flat, attribute-per-element SVGs this tool expects
+/// (logos, labels) without pulling in a full XML parser.
+fn svg_attribute(text: &str, name: &str) -> Option<String> {
+    let needle = format!("{}=\"", name);
+    let mut search_start = 0;
+    while let Some(relative_pos) = text[search_start..].find(&needle) {
+        let pos = search_start + relative_pos;
+        let at_boundary = pos == 0
+            || matches!(text[..pos].chars().next_back(), Some(c) if c.is_whitespace() || c == '<');
+        if at_boundary {
+            let start = pos + needle.len();
+            let end = start + text[start..].find('"')?;
+            return Some(text[start..end].to_string());
+        }
+        search_start = pos + needle.len();
+    }
+    None
+}
+
+enum SvgElement {
+    Rect { x: f32, y: f32, width: f32, height: f32, fill: Option<(f32, f32, f32)> },
+    Circle { cx: f32, cy: f32, r: f32, fill: Option<(f32, f32, f32)> },
+    Line { x1: f32, y1: f32, x2: f32, y2: f32 },
+    Path { commands: Vec<PathCommand>, fill: Option<(f32, f32, f32)> },
+    /// An embedded raster image: decoded and placed as an image XObject in [`add_svg_page`].
+    Image { x: f32, y: f32, width: f32, height: f32, href: String },
+    /// A tag this tool doesn't render at all (gradients, filters, masks, patterns as paint servers).
+    Unsupported(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum PathCommand {
+    /// Starts a new subpath; [`add_svg_page`] splits the command stream into one shape per
+    /// `MoveTo` rather than connecting subpaths together.
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    /// A bezier control point between the previous anchor and the next `LineTo`/`MoveTo`.
+    CurveControl(f32, f32),
+    /// `Z`/`z`: the current subpath is explicitly closed. Only matters for unfilled paths, whose
+    /// outline is drawn open unless the subpath ends in `ClosePath`; filled paths are always closed.
+    ClosePath,
+}
+
+/// One subpath out of a (possibly multi-`M`) path's command stream, with its drawable commands
+/// (`ClosePath` stripped out) and whether it ended in an explicit `Z`.
+struct SvgSubpath {
+    commands: Vec<PathCommand>,
+    closed: bool,
+}
+
+/// Splits a path's flat command stream into one [`SvgSubpath`] per `MoveTo`, so a logo made of
+/// several disjoint shapes in one `d` attribute doesn't get rendered as a single shape with
+/// spurious edges connecting them.
+fn split_svg_subpaths(commands: &[PathCommand]) -> Vec<SvgSubpath> {
+    let mut subpaths = Vec::new();
+    let mut current = Vec::new();
+    let mut closed = false;
+    for cmd in commands {
+        match cmd {
+            PathCommand::MoveTo(x, y) => {
+                if !current.is_empty() {
+                    subpaths.push(SvgSubpath { commands: std::mem::take(&mut current), closed });
+                    closed = false;
+                }
+                current.push(PathCommand::MoveTo(*x, *y));
+            }
+            PathCommand::LineTo(x, y) => current.push(PathCommand::LineTo(*x, *y)),
+            PathCommand::CurveControl(x, y) => current.push(PathCommand::CurveControl(*x, *y)),
+            PathCommand::ClosePath => closed = true,
+        }
+    }
+    if !current.is_empty() {
+        subpaths.push(SvgSubpath { commands: current, closed });
+    }
+    subpaths
+}
+
+const UNSUPPORTED_SVG_TAGS: &[&str] = &["linearGradient", "radialGradient", "filter", "mask", "pattern"];
+
+/// Walks the SVG's top-level shape tree tag by tag. Supports `rect`, `circle`, `line`, `path`
+/// (absolute/relative `M`/`L`/`H`/`V`/`C`/`S`/`Q`/`A`/`Z`), and `image` (rasterized as an embedded
+/// XObject); everything else becomes [`SvgElement::Unsupported`].
+fn parse_svg_elements(svg_text: &str) -> Vec<SvgElement> {
+    let mut elements = Vec::new();
+    let mut rest = svg_text;
+    while let Some(tag_start) = rest.find('<') {
+        let after_lt = &rest[tag_start + 1..];
+        let Some(tag_end) = after_lt.find('>') else { break };
+        let tag_body = &after_lt[..tag_end];
+        rest = &after_lt[tag_end + 1..];
+
+        if tag_body.starts_with('/') || tag_body.starts_with('?') || tag_body.starts_with('!') {
+            continue;
+        }
+        let tag_name = tag_body.split_whitespace().next().unwrap_or("").trim_end_matches('/');
+
+        match tag_name {
+            "svg" | "g" | "defs" | "title" | "desc" => continue, // containers, not shapes
+            "rect" => elements.push(SvgElement::Rect {
+                x: svg_attribute(tag_body, "x").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                y: svg_attribute(tag_body, "y").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                width: svg_attribute(tag_body, "width").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                height: svg_attribute(tag_body, "height").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                fill: svg_attribute(tag_body, "fill").and_then(|v| parse_svg_color(&v)),
+            }),
+            "circle" => elements.push(SvgElement::Circle {
+                cx: svg_attribute(tag_body, "cx").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                cy: svg_attribute(tag_body, "cy").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                r: svg_attribute(tag_body, "r").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                fill: svg_attribute(tag_body, "fill").and_then(|v| parse_svg_color(&v)),
+            }),
+            "line" => elements.push(SvgElement::Line {
+                x1: svg_attribute(tag_body, "x1").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                y1: svg_attribute(tag_body, "y1").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                x2: svg_attribute(tag_body, "x2").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                y2: svg_attribute(tag_body, "y2").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+            }),
+            "path" => {
+                let d = svg_attribute(tag_body, "d").unwrap_or_default();
+                elements.push(SvgElement::Path {
+                    commands: parse_svg_path_commands(&d),
+                    fill: svg_attribute(tag_body, "fill").and_then(|v| parse_svg_color(&v)),
+                });
+            }
+            "image" => elements.push(SvgElement::Image {
+                x: svg_attribute(tag_body, "x").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                y: svg_attribute(tag_body, "y").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                width: svg_attribute(tag_body, "width").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                height: svg_attribute(tag_body, "height").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                href: svg_attribute(tag_body, "href")
+                    .or_else(|| svg_attribute(tag_body, "xlink:href"))
+                    .unwrap_or_default(),
+            }),
+            "" => {}
+            other if UNSUPPORTED_SVG_TAGS.contains(&other) => {
+                elements.push(SvgElement::Unsupported(other.to_string()))
+            }
+            _ => {} // unknown element we simply don't draw (text, use, symbol, ...)
+        }
+    }
+    elements
+}
+
+/// Parses a `fill="..."` value. Supports `#rrggbb` hex and the bare keyword `none`; anything else
+/// (named colors, `url(#gradient)`, `rgb(...)`) is treated as unsupported and falls back to black.
+fn parse_svg_color(value: &str) -> Option<(f32, f32, f32)> {
+    if value == "none" {
+        return None;
+    }
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() == 6 {
+            let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+            let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+            let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+            return Some((r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0));
+        }
+    }
+    Some((0.0, 0.0, 0.0))
+}
+
+/// A token from an SVG path `d` attribute: a command letter or a number.
+enum PathToken {
+    Command(char),
+    Number(f32),
+}
+
+/// Parses the `M`/`L`/`H`/`V`/`C`/`S`/`Q`/`A`/`Z` subset of an SVG path `d` attribute, in both
+/// absolute (uppercase) and relative (lowercase) forms, including the implicit repeated
+/// coordinate groups real-world path data relies on (e.g. `L 1 2 3 4` is two line-tos, and
+/// coordinate pairs following an `M` beyond the first are implicit `L`s). `C`'s two control points
+/// are emitted as [`PathCommand::CurveControl`] so [`add_svg_page`] can hand them to printpdf as
+/// real bezier control points; `S`/`Q` are elevated/approximated to the same cubic form, and `A`
+/// (elliptical arcs) is approximated as a straight line to its endpoint.
+fn parse_svg_path_commands(d: &str) -> Vec<PathCommand> {
+    let tokens = tokenize_svg_path(d);
+    let mut commands = Vec::new();
+    let (mut x, mut y) = (0.0f32, 0.0f32);
+    let (mut start_x, mut start_y) = (0.0f32, 0.0f32);
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let cmd = match tokens[i] {
+            PathToken::Command(c) => c,
+            PathToken::Number(_) => {
+                i += 1;
+                continue;
+            }
+        };
+        i += 1;
+        let is_relative = cmd.is_ascii_lowercase();
+        let mut active_cmd = cmd;
+        let nargs = svg_path_arg_count(cmd);
+
+        if nargs == 0 {
+            // Z/z: close the subpath back to its starting point.
+            commands.push(PathCommand::ClosePath);
+            x = start_x;
+            y = start_y;
+            continue;
+        }
+
+        loop {
+            if i + nargs > tokens.len() {
+                break;
+            }
+            let mut nums = Vec::with_capacity(nargs);
+            let mut ok = true;
+            for k in 0..nargs {
+                match tokens[i + k] {
+                    PathToken::Number(n) => nums.push(n),
+                    PathToken::Command(_) => {
+                        ok = false;
+                        break;
+                    }
+                }
+            }
+            if !ok {
+                break;
+            }
+            i += nargs;
+            apply_svg_path_args(active_cmd, is_relative, &nums, &mut x, &mut y, &mut start_x, &mut start_y, &mut commands);
+
+            // Coordinate pairs after the first following an M/m are implicit L/l commands.
+            if active_cmd.eq_ignore_ascii_case(&'M') {
+                active_cmd = if is_relative { 'l' } else { 'L' };
+            }
+            if !matches!(tokens.get(i), Some(PathToken::Number(_))) {
+                break;
+            }
+        }
+    }
+
+    commands
+}
+
+/// Splits a path `d` attribute into command letters and numbers, handling the comma/whitespace
+/// separators SVG allows to be omitted (e.g. a `-` or a second `.` can itself start a new number).
+fn tokenize_svg_path(d: &str) -> Vec<PathToken> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut seen_dot = false;
+
+    fn flush(current: &mut String, seen_dot: &mut bool, tokens: &mut Vec<PathToken>) {
+        if !current.is_empty() {
+            if let Ok(n) = current.parse::<f32>() {
+                tokens.push(PathToken::Number(n));
+            }
+            current.clear();
+        }
+        *seen_dot = false;
+    }
+
+    for ch in d.chars() {
+        if ch.is_ascii_alphabetic() {
+            flush(&mut current, &mut seen_dot, &mut tokens);
+            tokens.push(PathToken::Command(ch));
+        } else if ch == '-' || ch == '+' {
+            if !current.is_empty() && !current.ends_with(['e', 'E']) {
+                flush(&mut current, &mut seen_dot, &mut tokens);
+            }
+            current.push(ch);
+        } else if ch == '.' {
+            if seen_dot {
+                flush(&mut current, &mut seen_dot, &mut tokens);
+            }
+            seen_dot = true;
+            current.push(ch);
+        } else if ch.is_ascii_digit() || ch == 'e' || ch == 'E' {
+            current.push(ch);
+        } else {
+            flush(&mut current, &mut seen_dot, &mut tokens);
+        }
+    }
+    flush(&mut current, &mut seen_dot, &mut tokens);
+
+    tokens
+}
+
+/// Number of coordinate arguments a path command letter consumes per repetition.
+fn svg_path_arg_count(cmd: char) -> usize {
+    match cmd.to_ascii_uppercase() {
+        'M' | 'L' | 'T' => 2,
+        'H' | 'V' => 1,
+        'S' | 'Q' => 4,
+        'C' => 6,
+        'A' => 7,
+        _ => 0, // Z/z and anything unrecognized
+    }
+}
+
+/// Applies one command repetition's arguments, updating the running current point (and subpath
+/// start, for `M`) and pushing the resulting [`PathCommand`]s.
+fn apply_svg_path_args(
+    cmd: char,
+    is_relative: bool,
+    nums: &[f32],
+    x: &mut f32,
+    y: &mut f32,
+    start_x: &mut f32,
+    start_y: &mut f32,
+    out: &mut Vec<PathCommand>,
+) {
+    let abs_x = |v: f32, cur: f32| if is_relative { cur + v } else { v };
+    match cmd.to_ascii_uppercase() {
+        'M' => {
+            let (nx, ny) = (abs_x(nums[0], *x), abs_x(nums[1], *y));
+            *x = nx;
+            *y = ny;
+            *start_x = nx;
+            *start_y = ny;
+            out.push(PathCommand::MoveTo(nx, ny));
+        }
+        'L' => {
+            let (nx, ny) = (abs_x(nums[0], *x), abs_x(nums[1], *y));
+            *x = nx;
+            *y = ny;
+            out.push(PathCommand::LineTo(nx, ny));
+        }
+        'H' => {
+            *x = abs_x(nums[0], *x);
+            out.push(PathCommand::LineTo(*x, *y));
+        }
+        'V' => {
+            *y = abs_x(nums[0], *y);
+            out.push(PathCommand::LineTo(*x, *y));
+        }
+        'C' => {
+            let (x1, y1) = (abs_x(nums[0], *x), abs_x(nums[1], *y));
+            let (x2, y2) = (abs_x(nums[2], *x), abs_x(nums[3], *y));
+            let (ex, ey) = (abs_x(nums[4], *x), abs_x(nums[5], *y));
+            out.push(PathCommand::CurveControl(x1, y1));
+            out.push(PathCommand::CurveControl(x2, y2));
+            out.push(PathCommand::LineTo(ex, ey));
+            *x = ex;
+            *y = ey;
+        }
+        'S' => {
+            // No memory of the previous curve's control point is kept, so the reflected first
+            // control point is approximated as the current point (a straight-ish entry).
+            let (x1, y1) = (*x, *y);
+            let (x2, y2) = (abs_x(nums[0], *x), abs_x(nums[1], *y));
+            let (ex, ey) = (abs_x(nums[2], *x), abs_x(nums[3], *y));
+            out.push(PathCommand::CurveControl(x1, y1));
+            out.push(PathCommand::CurveControl(x2, y2));
+            out.push(PathCommand::LineTo(ex, ey));
+            *x = ex;
+            *y = ey;
+        }
+        'Q' => {
+            let (qx, qy) = (abs_x(nums[0], *x), abs_x(nums[1], *y));
+            let (ex, ey) = (abs_x(nums[2], *x), abs_x(nums[3], *y));
+            // Elevate the single quadratic control point to the two cubic ones printpdf expects.
+            let (c1x, c1y) = (*x + 2.0 / 3.0 * (qx - *x), *y + 2.0 / 3.0 * (qy - *y));
+            let (c2x, c2y) = (ex + 2.0 / 3.0 * (qx - ex), ey + 2.0 / 3.0 * (qy - ey));
+            out.push(PathCommand::CurveControl(c1x, c1y));
+            out.push(PathCommand::CurveControl(c2x, c2y));
+            out.push(PathCommand::LineTo(ex, ey));
+            *x = ex;
+            *y = ey;
+        }
+        'T' => {
+            // Smooth quadratic: approximated as a straight line (no reflected control memory).
+            let (ex, ey) = (abs_x(nums[0], *x), abs_x(nums[1], *y));
+            out.push(PathCommand::LineTo(ex, ey));
+            *x = ex;
+            *y = ey;
+        }
+        'A' => {
+            // Elliptical arcs are approximated as a straight line to the endpoint; good enough for
+            // small UI glyphs, not for genuinely curved arcs.
+            let (ex, ey) = (abs_x(nums[5], *x), abs_x(nums[6], *y));
+            out.push(PathCommand::LineTo(ex, ey));
+            *x = ex;
+            *y = ey;
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal JPEG byte stream with a single SOF segment, just enough for
+    /// [`parse_jpeg_sof`] to read width/height/components without needing real entropy-coded data.
+    fn fake_jpeg(sof_marker: u8, width: u16, height: u16, components: u8) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8]; // SOI
+        data.extend_from_slice(&[0xFF, sof_marker, 0x00, 0x08]); // marker + segment length (unused past payload)
+        data.push(0x08); // precision
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&width.to_be_bytes());
+        data.push(components);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00]); // padding past the fields we read
+        data.extend_from_slice(&[0xFF, 0xD9]); // EOI
+        data
+    }
+
+    #[test]
+    fn parse_jpeg_sof_reads_baseline_frame() {
+        let data = fake_jpeg(0xC0, 640, 480, 3);
+        assert_eq!(parse_jpeg_sof(&data), Some((640, 480, 3, true)));
+    }
+
+    #[test]
+    fn parse_jpeg_sof_reads_progressive_frame() {
+        let data = fake_jpeg(0xC2, 640, 480, 3);
+        assert_eq!(parse_jpeg_sof(&data), Some((640, 480, 3, false)));
+    }
+
+    #[test]
+    fn parse_jpeg_sof_reads_cmyk_component_count() {
+        let data = fake_jpeg(0xC0, 100, 100, 4);
+        assert_eq!(parse_jpeg_sof(&data), Some((100, 100, 4, true)));
+    }
+
+    #[test]
+    fn parse_jpeg_sof_rejects_non_jpeg() {
+        assert_eq!(parse_jpeg_sof(b"not a jpeg"), None);
+    }
+
+    /// Builds a minimal single-IFD TIFF/EXIF blob holding just an Orientation (0x0112) SHORT tag,
+    /// for [`parse_tiff_orientation`].
+    fn fake_tiff_orientation(little_endian: bool, orientation: u16) -> Vec<u8> {
+        let put16 = |out: &mut Vec<u8>, v: u16| {
+            if little_endian {
+                out.extend_from_slice(&v.to_le_bytes());
+            } else {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        };
+        let put32 = |out: &mut Vec<u8>, v: u32| {
+            if little_endian {
+                out.extend_from_slice(&v.to_le_bytes());
+            } else {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        };
+
+        let mut tiff = Vec::new();
+        tiff.extend_from_slice(if little_endian { b"II" } else { b"MM" });
+        put16(&mut tiff, 42); // TIFF magic number, unchecked by parse_tiff_orientation
+        put32(&mut tiff, 8); // IFD0 offset
+        put16(&mut tiff, 1); // one entry
+        put16(&mut tiff, 0x0112); // tag: Orientation
+        put16(&mut tiff, 3); // type: SHORT
+        put32(&mut tiff, 1); // count
+        put16(&mut tiff, orientation); // value, inline in the first 2 bytes of the value field
+        put16(&mut tiff, 0); // padding out the rest of the 4-byte value field
+        tiff
+    }
+
+    #[test]
+    fn parse_tiff_orientation_reads_little_endian() {
+        let tiff = fake_tiff_orientation(true, 6);
+        assert_eq!(parse_tiff_orientation(&tiff), Some(6));
+    }
+
+    #[test]
+    fn parse_tiff_orientation_reads_big_endian() {
+        let tiff = fake_tiff_orientation(false, 8);
+        assert_eq!(parse_tiff_orientation(&tiff), Some(8));
+    }
+
+    #[test]
+    fn parse_tiff_orientation_missing_tag_returns_none() {
+        // Same shape as fake_tiff_orientation but tagged 0x0110 (Model) instead of 0x0112.
+        let mut tiff = Vec::new();
+        tiff.extend_from_slice(b"II");
+        tiff.extend_from_slice(&42u16.to_le_bytes());
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&0x0110u16.to_le_bytes());
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        tiff.extend_from_slice(&9u16.to_le_bytes());
+        tiff.extend_from_slice(&0u16.to_le_bytes());
+        assert_eq!(parse_tiff_orientation(&tiff), None);
+    }
+
+    #[test]
+    fn parse_tiff_orientation_too_short_returns_none() {
+        assert_eq!(parse_tiff_orientation(b"II*"), None);
+    }
+
+    #[test]
+    fn orientation_transform_normal_is_a_no_op() {
+        let t = orientation_transform(1);
+        assert_eq!(t.rotate_ccw_degrees, None);
+        assert_eq!((t.mirror_h_scale, t.mirror_v_scale), (1.0, 1.0));
+        assert!(!t.swap_dimensions);
+    }
+
+    #[test]
+    fn orientation_transform_6_rotates_without_swap_confusion() {
+        let t = orientation_transform(6);
+        assert_eq!(t.rotate_ccw_degrees, Some(270.0));
+        assert!(t.swap_dimensions);
+    }
+
+    #[test]
+    fn orientation_transform_unknown_value_falls_back_to_normal() {
+        let t = orientation_transform(42);
+        assert_eq!(t.rotate_ccw_degrees, None);
+        assert!(!t.swap_dimensions);
+    }
+
+    #[test]
+    fn imposition_grid_known_sizes() {
+        assert_eq!(imposition_grid(1), (1, 1));
+        assert_eq!(imposition_grid(2), (1, 2));
+        assert_eq!(imposition_grid(4), (2, 2));
+        assert_eq!(imposition_grid(6), (3, 2));
+    }
+
+    #[test]
+    fn imposition_grid_clamps_zero_to_one() {
+        assert_eq!(imposition_grid(0), (1, 1));
+    }
+
+    #[test]
+    fn parse_resolution_parses_explicit_and_square_dpi() {
+        assert_eq!(parse_resolution("300x600"), Some((300, 600)));
+        assert_eq!(parse_resolution("300"), Some((300, 300)));
+        assert_eq!(parse_resolution(" 150 x 150 "), Some((150, 150)));
+    }
+
+    #[test]
+    fn parse_resolution_rejects_garbage() {
+        assert_eq!(parse_resolution("dpi"), None);
+    }
+
+    #[test]
+    fn rle_encode_row_collapses_runs_and_keeps_distinct_pixels() {
+        // Three identical grayscale pixels then one different one.
+        let row = [10, 10, 10, 20];
+        let encoded = rle_encode_row(&row, 1);
+        assert_eq!(encoded, vec![2, 10, 0, 20]);
+    }
+
+    #[test]
+    fn base64_decode_round_trips_known_vector() {
+        assert_eq!(base64_decode("aGVsbG8=").unwrap(), b"hello");
+    }
+
+    #[test]
+    fn base64_decode_rejects_invalid_byte() {
+        assert!(base64_decode("not!base64").is_err());
+    }
+
+    #[test]
+    fn parse_svg_path_commands_splits_multiple_subpaths_on_moveto() {
+        let commands = parse_svg_path_commands("M0 0 L10 0 L10 10 Z M20 20 L30 20");
+        assert_eq!(
+            commands,
+            vec![
+                PathCommand::MoveTo(0.0, 0.0),
+                PathCommand::LineTo(10.0, 0.0),
+                PathCommand::LineTo(10.0, 10.0),
+                PathCommand::ClosePath,
+                PathCommand::MoveTo(20.0, 20.0),
+                PathCommand::LineTo(30.0, 20.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_svg_path_commands_implicit_lineto_after_moveto() {
+        // A second coordinate pair after M with no command letter is an implicit L.
+        let commands = parse_svg_path_commands("M0 0 10 10");
+        assert_eq!(
+            commands,
+            vec![PathCommand::MoveTo(0.0, 0.0), PathCommand::LineTo(10.0, 10.0)],
+        );
+    }
+
+    #[test]
+    fn parse_svg_path_commands_relative_commands_accumulate_from_current_point() {
+        let commands = parse_svg_path_commands("M10 10 l5 5");
+        assert_eq!(
+            commands,
+            vec![PathCommand::MoveTo(10.0, 10.0), PathCommand::LineTo(15.0, 15.0)],
+        );
+    }
+}